@@ -0,0 +1,195 @@
+//! `AsyncRead`/`AsyncWrite` impls for [`ReadHalf`]/[`WriteHalf`], gated behind
+//! the `tokio` feature. These mirror the blocking `io::Read`/`io::Write` impls
+//! in `lib.rs` but drive the underlying transport's poll-based I/O instead of
+//! blocking on it.
+
+use std::{
+    io,
+    io::{Read, Write},
+    pin::Pin,
+    sync::MutexGuard,
+    task::{Context, Poll},
+};
+
+use rustls::Connection;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+use crate::{buffer::AsyncTransport, ReadHalf, Shared, WriteBuffer, WriteHalf};
+
+impl<S> AsyncRead for ReadHalf<S>
+where
+    S: AsyncTransport + Unpin,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        out: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        let mut connection = this.shared.connection.lock().unwrap();
+
+        while connection.wants_read() {
+            if this.buf.is_empty() {
+                drop(connection);
+
+                let bytes_read = match this.buf.poll_read_from(cx, &this.shared.stream) {
+                    Poll::Ready(Ok(n)) => n,
+                    Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                    Poll::Pending => return Poll::Pending,
+                };
+
+                connection = this.shared.connection.lock().unwrap();
+
+                if bytes_read == 0 {
+                    break;
+                }
+            }
+
+            let bytes_read = match connection.read_tls(&mut this.buf) {
+                Ok(n) => n,
+                Err(e) => return Poll::Ready(Err(e)),
+            };
+            debug_assert_ne!(bytes_read, 0);
+
+            if let Err(e) = connection.process_new_packets() {
+                return Poll::Ready(Err(io::Error::new(io::ErrorKind::InvalidData, e)));
+            }
+        }
+
+        let result = match connection.reader().read(out.initialize_unfilled()) {
+            Ok(0) => Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "TLS connection closed improperly",
+            )),
+            ok @ Ok(_) => ok,
+            Err(ref e) if e.kind() == io::ErrorKind::ConnectionAborted => Ok(0),
+            err @ Err(_) => err,
+        };
+        drop(connection);
+
+        match this.throttle(result) {
+            Ok(n) => {
+                out.advance(n);
+                Poll::Ready(Ok(()))
+            }
+            Err(e) => Poll::Ready(Err(e)),
+        }
+    }
+}
+
+fn poll_wants_write_loop<'a, S>(
+    cx: &mut Context<'_>,
+    buf: &mut WriteBuffer,
+    shared: &'a Shared<S>,
+    mut connection: MutexGuard<'a, Connection>,
+) -> Poll<io::Result<MutexGuard<'a, Connection>>>
+where
+    S: AsyncTransport,
+{
+    while connection.wants_write() {
+        while buf.is_full() {
+            drop(connection);
+
+            match buf.poll_write_to(cx, &shared.stream) {
+                Poll::Ready(Ok(_)) => {}
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                Poll::Pending => return Poll::Pending,
+            }
+
+            connection = shared.connection.lock().unwrap();
+        }
+
+        if let Err(e) = connection.write_tls(buf) {
+            return Poll::Ready(Err(e));
+        }
+    }
+
+    Poll::Ready(Ok(connection))
+}
+
+fn poll_flush<'a, S>(
+    cx: &mut Context<'_>,
+    buf: &mut WriteBuffer,
+    shared: &'a Shared<S>,
+    mut connection: MutexGuard<'a, Connection>,
+) -> Poll<io::Result<()>>
+where
+    S: AsyncTransport,
+{
+    if let Err(e) = connection.writer().flush() {
+        return Poll::Ready(Err(e));
+    }
+
+    connection = match poll_wants_write_loop(cx, buf, shared, connection) {
+        Poll::Ready(Ok(connection)) => connection,
+        Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+        Poll::Pending => return Poll::Pending,
+    };
+    drop(connection);
+
+    while !buf.is_empty() {
+        match buf.poll_write_to(cx, &shared.stream) {
+            Poll::Ready(Ok(_)) => {}
+            Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+            Poll::Pending => return Poll::Pending,
+        }
+    }
+
+    Poll::Ready(Ok(()))
+}
+
+impl<S> AsyncWrite for WriteHalf<S>
+where
+    S: AsyncTransport + Unpin,
+{
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        let connection = this.shared.connection.lock().unwrap();
+
+        let mut connection =
+            match poll_wants_write_loop(cx, &mut this.buf, &this.shared, connection) {
+                Poll::Ready(Ok(connection)) => connection,
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                Poll::Pending => return Poll::Pending,
+            };
+
+        // Mirrors the sync `WriteHalf::write` in lib.rs: while the handshake
+        // is still in progress, a `ClientConnection` may still accept TLS 1.3
+        // 0-RTT early data, so `split_early_data` keeps working under the
+        // `tokio` feature too.
+        if let Connection::Client(client) = &mut *connection {
+            if let Some(mut early_data) = client.early_data() {
+                let result = early_data.write(buf);
+                drop(connection);
+                return Poll::Ready(this.throttle(result));
+            }
+        }
+
+        let result = connection.writer().write(buf);
+        drop(connection);
+        Poll::Ready(this.throttle(result))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        let connection = this.shared.connection.lock().unwrap();
+        poll_flush(cx, &mut this.buf, &this.shared, connection)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        let mut connection = this.shared.connection.lock().unwrap();
+        connection.send_close_notify();
+
+        match poll_flush(cx, &mut this.buf, &this.shared, connection) {
+            Poll::Ready(Ok(())) => {
+                Poll::Ready(this.shared.stream.shutdown_priv(std::net::Shutdown::Write))
+            }
+            other => other,
+        }
+    }
+}
@@ -1,5 +1,96 @@
 use std::io;
 
+#[cfg(feature = "tokio")]
+use std::task::{Context, Poll};
+
+/// What the async halves (behind the `tokio` feature) need from their
+/// transport: readiness-based reads and writes through a shared `&self`,
+/// the way the sync halves rely on `for<'a> &'a S: io::Read`/`io::Write`
+/// (which [`std::net::TcpStream`] meets) plus [`HalfClose`](crate::HalfClose)
+/// for half-closing.
+/// [`tokio::io::AsyncRead`]/`AsyncWrite` can't be used for that bound
+/// directly: `tokio::net::TcpStream` only implements them for the owned
+/// type, not `&TcpStream`, so splitting one `TcpStream` across both halves
+/// needs this instead.
+#[cfg(feature = "tokio")]
+pub trait AsyncTransport {
+    fn poll_read_priv(&self, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<io::Result<usize>>;
+
+    fn poll_write_priv(&self, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>>;
+
+    fn poll_write_vectored_priv(
+        &self,
+        cx: &mut Context<'_>,
+        bufs: &[io::IoSlice<'_>],
+    ) -> Poll<io::Result<usize>>;
+
+    /// Half-closes the transport the way [`crate::HalfClose`] does for the
+    /// sync halves, through a shared `&self` rather than an owned value.
+    fn shutdown_priv(&self, how: std::net::Shutdown) -> io::Result<()>;
+}
+
+#[cfg(feature = "tokio")]
+impl AsyncTransport for tokio::net::TcpStream {
+    fn poll_read_priv(&self, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+        loop {
+            match self.poll_read_ready(cx) {
+                Poll::Ready(Ok(())) => match self.try_read(buf) {
+                    Err(e) if e.kind() == io::ErrorKind::WouldBlock => continue,
+                    result => return Poll::Ready(result),
+                },
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+
+    fn poll_write_priv(&self, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        loop {
+            match self.poll_write_ready(cx) {
+                Poll::Ready(Ok(())) => match self.try_write(buf) {
+                    Err(e) if e.kind() == io::ErrorKind::WouldBlock => continue,
+                    result => return Poll::Ready(result),
+                },
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+
+    fn poll_write_vectored_priv(
+        &self,
+        cx: &mut Context<'_>,
+        bufs: &[io::IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        loop {
+            match self.poll_write_ready(cx) {
+                Poll::Ready(Ok(())) => match self.try_write_vectored(bufs) {
+                    Err(e) if e.kind() == io::ErrorKind::WouldBlock => continue,
+                    result => return Poll::Ready(result),
+                },
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+
+    // `TcpStream::poll_shutdown` (the `AsyncWrite` impl) needs `Pin<&mut
+    // Self>`, which we don't have from a shared `&Shared<S>.stream`. A
+    // `shutdown(2)` doesn't actually need exclusive access to the socket, so
+    // we borrow the raw fd just long enough to issue it through
+    // `std::net::TcpStream`, the same way `HalfClose` does for the sync
+    // halves, then let the borrow go without closing the real fd.
+    #[cfg(unix)]
+    fn shutdown_priv(&self, how: std::net::Shutdown) -> io::Result<()> {
+        use std::os::fd::{AsRawFd, FromRawFd};
+
+        let borrowed = std::mem::ManuallyDrop::new(unsafe {
+            std::net::TcpStream::from_raw_fd(self.as_raw_fd())
+        });
+        borrowed.shutdown(how)
+    }
+}
+
 pub struct BufCfg<D: Into<Vec<u8>>> {
     initial_data: D,
     min_capacity: usize,
@@ -53,10 +144,6 @@ impl Internals {
         self.end == 0
     }
 
-    fn is_full(&self) -> bool {
-        self.end == self.buf.len()
-    }
-
     fn advance_start(&mut self, delta: usize) {
         self.start += delta;
 
@@ -87,6 +174,22 @@ impl ReadBuffer {
         self.internals.end += bytes_read;
         Ok(bytes_read)
     }
+
+    #[cfg(feature = "tokio")]
+    pub fn poll_read_from(
+        &mut self,
+        cx: &mut Context<'_>,
+        reader: &impl AsyncTransport,
+    ) -> Poll<io::Result<usize>> {
+        match reader.poll_read_priv(cx, &mut self.internals.buf[self.internals.end..]) {
+            Poll::Ready(Ok(bytes_read)) => {
+                self.internals.end += bytes_read;
+                Poll::Ready(Ok(bytes_read))
+            }
+            Poll::Ready(Err(e)) => Poll::Ready(Err(e)),
+            Poll::Pending => Poll::Pending,
+        }
+    }
 }
 
 impl io::Read for ReadBuffer {
@@ -99,39 +202,117 @@ impl io::Read for ReadBuffer {
     }
 }
 
+/// Unlike [`ReadBuffer`]/[`Internals`], which reset to the front only once
+/// fully drained, `WriteBuffer` wraps: once the tail reaches the end of
+/// `buf` it resumes filling from the front, as long as `start` has moved
+/// past it. This lets a buffer full of ciphertext straddle the end of the
+/// array as two contiguous segments, which [`WriteBuffer::write_to`] and
+/// [`WriteBuffer::poll_write_to`] flush to the transport with a single
+/// vectored write instead of two separate ones.
 pub struct WriteBuffer {
-    internals: Internals,
+    buf: Box<[u8]>,
+    start: usize,
+    len: usize,
 }
 
 impl WriteBuffer {
     pub fn build_from<D: Into<Vec<u8>>>(cfg: BufCfg<D>) -> Self {
+        let internals = Internals::build_from(cfg);
+        let len = internals.end;
         Self {
-            internals: Internals::build_from(cfg),
+            buf: internals.buf,
+            start: 0,
+            len,
         }
     }
 
+    fn capacity(&self) -> usize {
+        self.buf.len()
+    }
+
     pub fn is_empty(&self) -> bool {
-        self.internals.is_empty()
+        self.len == 0
     }
 
     pub fn is_full(&self) -> bool {
-        self.internals.is_full()
+        self.len == self.capacity()
+    }
+
+    /// The occupied region as up to two contiguous segments, in the order
+    /// they should be flushed. The second segment is non-empty only once
+    /// the tail has wrapped around past the end of `buf`.
+    fn segments(&self) -> (&[u8], &[u8]) {
+        if self.len == 0 {
+            return (&[], &[]);
+        }
+
+        let first_len = (self.capacity() - self.start).min(self.len);
+        let first = &self.buf[self.start..self.start + first_len];
+        let second = &self.buf[..self.len - first_len];
+        (first, second)
+    }
+
+    fn advance_start(&mut self, delta: usize) {
+        self.start = (self.start + delta) % self.capacity();
+        self.len -= delta;
+
+        if self.len == 0 {
+            self.start = 0;
+        }
     }
 
     pub fn write_to(&mut self, writer: &mut impl io::Write) -> io::Result<usize> {
-        let bytes_written =
-            writer.write(&self.internals.buf[self.internals.start..self.internals.end])?;
-        self.internals.advance_start(bytes_written);
+        let (first, second) = self.segments();
+
+        let bytes_written = if second.is_empty() {
+            writer.write(first)?
+        } else {
+            writer.write_vectored(&[io::IoSlice::new(first), io::IoSlice::new(second)])?
+        };
+
+        self.advance_start(bytes_written);
         Ok(bytes_written)
     }
+
+    #[cfg(feature = "tokio")]
+    pub fn poll_write_to(
+        &mut self,
+        cx: &mut Context<'_>,
+        writer: &impl AsyncTransport,
+    ) -> Poll<io::Result<usize>> {
+        let (first, second) = self.segments();
+
+        let result = if second.is_empty() {
+            writer.poll_write_priv(cx, first)
+        } else {
+            writer.poll_write_vectored_priv(cx, &[io::IoSlice::new(first), io::IoSlice::new(second)])
+        };
+
+        match result {
+            Poll::Ready(Ok(bytes_written)) => {
+                self.advance_start(bytes_written);
+                Poll::Ready(Ok(bytes_written))
+            }
+            Poll::Ready(Err(e)) => Poll::Ready(Err(e)),
+            Poll::Pending => Poll::Pending,
+        }
+    }
 }
 
 impl io::Write for WriteBuffer {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        let dst = &mut self.internals.buf[self.internals.end..];
-        let len = std::cmp::min(dst.len(), buf.len());
-        dst[..len].copy_from_slice(&buf[..len]);
-        self.internals.end += len;
+        let cap = self.capacity();
+        let len = buf.len().min(cap - self.len);
+        if len == 0 {
+            return Ok(0);
+        }
+
+        let tail = (self.start + self.len) % cap;
+        let first_len = (cap - tail).min(len);
+        self.buf[tail..tail + first_len].copy_from_slice(&buf[..first_len]);
+        self.buf[..len - first_len].copy_from_slice(&buf[first_len..len]);
+
+        self.len += len;
         Ok(len)
     }
 
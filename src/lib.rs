@@ -9,19 +9,105 @@ use rustls::Connection;
 
 mod buffer;
 pub use buffer::BufCfg;
-use buffer::Buffer;
+use buffer::{ReadBuffer, WriteBuffer};
 
-struct Shared {
-    stream: TcpStream,
+mod rate_limit;
+pub use rate_limit::RateLimitCfg;
+use rate_limit::{ByteCounter, RateLimiter};
+
+#[cfg(feature = "tokio")]
+mod asynchronous;
+
+/// The half-close behavior `split` needs from its transport, beyond plain
+/// `Read`/`Write`. Implemented for [`TcpStream`] so TCP keeps working out of
+/// the box; implement it for other transports (e.g. `UnixStream`) to split
+/// them too.
+pub trait HalfClose {
+    fn shutdown(&self, how: Shutdown) -> io::Result<()>;
+}
+
+impl HalfClose for TcpStream {
+    fn shutdown(&self, how: Shutdown) -> io::Result<()> {
+        TcpStream::shutdown(self, how)
+    }
+}
+
+struct Shared<S> {
+    stream: S,
     connection: Mutex<Connection>,
 }
 
-pub struct ReadHalf {
-    shared: Arc<Shared>,
-    buf: Buffer,
+impl<S> Shared<S> {
+    fn alpn_protocol(&self) -> Option<Vec<u8>> {
+        self.connection
+            .lock()
+            .unwrap()
+            .alpn_protocol()
+            .map(|protocol| protocol.to_vec())
+    }
+
+    fn peer_certificates(&self) -> Option<Vec<rustls::Certificate>> {
+        self.connection
+            .lock()
+            .unwrap()
+            .peer_certificates()
+            .map(|certs| certs.to_vec())
+    }
+
+    fn protocol_version(&self) -> Option<rustls::ProtocolVersion> {
+        self.connection.lock().unwrap().protocol_version()
+    }
+}
+
+pub struct ReadHalf<S> {
+    shared: Arc<Shared<S>>,
+    buf: ReadBuffer,
+    rate_limiter: Option<RateLimiter>,
+    bytes_read: ByteCounter,
 }
 
-impl io::Read for ReadHalf {
+impl<S> ReadHalf<S> {
+    /// The application-layer protocol negotiated during the handshake (e.g. via ALPN).
+    pub fn alpn_protocol(&self) -> Option<Vec<u8>> {
+        self.shared.alpn_protocol()
+    }
+
+    /// The peer's certificate chain, if the connection required one.
+    pub fn peer_certificates(&self) -> Option<Vec<rustls::Certificate>> {
+        self.shared.peer_certificates()
+    }
+
+    /// The TLS version negotiated during the handshake.
+    pub fn protocol_version(&self) -> Option<rustls::ProtocolVersion> {
+        self.shared.protocol_version()
+    }
+
+    /// Total plaintext bytes delivered to callers of `read` so far.
+    pub fn bytes_read(&self) -> u64 {
+        self.bytes_read.get()
+    }
+
+    /// Accounts for `result`'s bytes (if any) and, when a rate limit is
+    /// configured, blocks until the token bucket can afford them.
+    fn throttle(&self, result: io::Result<usize>) -> io::Result<usize> {
+        if let Ok(n) = result {
+            if n > 0 {
+                self.bytes_read.add(n as u64);
+
+                if let Some(rate_limiter) = &self.rate_limiter {
+                    rate_limiter.throttle(n as u64);
+                }
+            }
+        }
+
+        result
+    }
+}
+
+impl<S> io::Read for ReadHalf<S>
+where
+    for<'a> &'a S: io::Read,
+{
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
         let mut connection = self.shared.connection.lock().unwrap();
 
@@ -29,6 +115,11 @@ impl io::Read for ReadHalf {
             if self.buf.is_empty() {
                 drop(connection);
 
+                // On a non-blocking stream this surfaces `WouldBlock` as soon as the
+                // socket has nothing more to offer; `connection.wants_read()` being
+                // true here already guarantees there's no plaintext decrypted from
+                // an earlier read left to hand back instead, so there's nothing to
+                // do but propagate the error like any other.
                 let bytes_read = self.buf.read_from(&mut &self.shared.stream)?;
 
                 connection = self.shared.connection.lock().unwrap();
@@ -46,7 +137,7 @@ impl io::Read for ReadHalf {
                 .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
         }
 
-        match connection.reader().read(buf) {
+        let result = match connection.reader().read(buf) {
             Ok(0) => Err(io::Error::new(
                 io::ErrorKind::UnexpectedEof,
                 "TLS connection closed improperly",
@@ -54,22 +145,68 @@ impl io::Read for ReadHalf {
             ok @ Ok(_) => ok,
             Err(ref e) if e.kind() == io::ErrorKind::ConnectionAborted => Ok(0),
             err @ Err(_) => err,
-        }
+        };
+        drop(connection);
+        self.throttle(result)
     }
 }
 
-impl ReadHalf {
+impl<S: HalfClose> ReadHalf<S> {
     pub fn shutdown(&mut self, how: Shutdown) -> io::Result<()> {
         self.shared.stream.shutdown(how)
     }
 }
 
-pub struct WriteHalf {
-    shared: Arc<Shared>,
-    buf: Buffer,
+pub struct WriteHalf<S> {
+    shared: Arc<Shared<S>>,
+    buf: WriteBuffer,
+    rate_limiter: Option<RateLimiter>,
+    bytes_written: ByteCounter,
 }
 
-impl WriteHalf {
+impl<S> WriteHalf<S> {
+    /// The application-layer protocol negotiated during the handshake (e.g. via ALPN).
+    pub fn alpn_protocol(&self) -> Option<Vec<u8>> {
+        self.shared.alpn_protocol()
+    }
+
+    /// The peer's certificate chain, if the connection required one.
+    pub fn peer_certificates(&self) -> Option<Vec<rustls::Certificate>> {
+        self.shared.peer_certificates()
+    }
+
+    /// The TLS version negotiated during the handshake.
+    pub fn protocol_version(&self) -> Option<rustls::ProtocolVersion> {
+        self.shared.protocol_version()
+    }
+
+    /// Total plaintext bytes accepted by `write`/`write_vectored` so far.
+    pub fn bytes_written(&self) -> u64 {
+        self.bytes_written.get()
+    }
+
+    /// Accounts for `result`'s bytes (if any) and, when a rate limit is
+    /// configured, blocks until the token bucket can afford them.
+    fn throttle(&self, result: io::Result<usize>) -> io::Result<usize> {
+        if let Ok(n) = result {
+            if n > 0 {
+                self.bytes_written.add(n as u64);
+
+                if let Some(rate_limiter) = &self.rate_limiter {
+                    rate_limiter.throttle(n as u64);
+                }
+            }
+        }
+
+        result
+    }
+}
+
+impl<S> WriteHalf<S>
+where
+    for<'a> &'a S: io::Write,
+    S: HalfClose,
+{
     pub fn shutdown(&mut self, how: Shutdown) -> io::Result<()> {
         if how == Shutdown::Read {
             return self.shared.stream.shutdown(Shutdown::Read);
@@ -83,15 +220,21 @@ impl WriteHalf {
     }
 }
 
-fn wants_write_loop<'a>(
-    buf: &mut Buffer,
-    shared: &'a Shared,
+fn wants_write_loop<'a, S>(
+    buf: &mut WriteBuffer,
+    shared: &'a Shared<S>,
     mut connection: MutexGuard<'a, Connection>,
-) -> io::Result<MutexGuard<'a, Connection>> {
+) -> io::Result<MutexGuard<'a, Connection>>
+where
+    for<'b> &'b S: io::Write,
+{
     while connection.wants_write() {
         while buf.is_full() {
             drop(connection);
 
+            // On a non-blocking stream this surfaces `WouldBlock` as soon as the
+            // socket can't take a full write; `buf`'s start/len are only advanced
+            // by what actually got flushed, so the next call resumes cleanly.
             buf.write_to(&mut &shared.stream)?;
 
             connection = shared.connection.lock().unwrap();
@@ -103,11 +246,14 @@ fn wants_write_loop<'a>(
     Ok(connection)
 }
 
-fn flush<'a>(
-    buf: &mut Buffer,
-    shared: &'a Shared,
+fn flush<'a, S>(
+    buf: &mut WriteBuffer,
+    shared: &'a Shared<S>,
     mut connection: MutexGuard<'a, Connection>,
-) -> io::Result<()> {
+) -> io::Result<()>
+where
+    for<'b> &'b S: io::Write,
+{
     connection.writer().flush()?;
 
     let connection = wants_write_loop(buf, shared, connection)?;
@@ -120,11 +266,50 @@ fn flush<'a>(
     Ok(())
 }
 
-impl io::Write for WriteHalf {
+impl<S> io::Write for WriteHalf<S>
+where
+    for<'a> &'a S: io::Write,
+{
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
         let connection = self.shared.connection.lock().unwrap();
         let mut connection = wants_write_loop(&mut self.buf, &self.shared, connection)?;
-        connection.writer().write(buf)
+
+        // While the handshake is still in progress, a `ClientConnection` may
+        // still accept TLS 1.3 0-RTT early data; once the handshake completes
+        // (or the server rejects it) `early_data()` goes back to `None` and we
+        // fall through to the regular writer below.
+        if let Connection::Client(client) = &mut *connection {
+            if let Some(mut early_data) = client.early_data() {
+                let result = early_data.write(buf);
+                drop(connection);
+                return self.throttle(result);
+            }
+        }
+
+        let result = connection.writer().write(buf);
+        drop(connection);
+        self.throttle(result)
+    }
+
+    fn write_vectored(&mut self, bufs: &[io::IoSlice<'_>]) -> io::Result<usize> {
+        let connection = self.shared.connection.lock().unwrap();
+        let mut connection = wants_write_loop(&mut self.buf, &self.shared, connection)?;
+
+        if let Connection::Client(client) = &mut *connection {
+            if let Some(mut early_data) = client.early_data() {
+                let result = early_data.write_vectored(bufs);
+                drop(connection);
+                return self.throttle(result);
+            }
+        }
+
+        let result = connection.writer().write_vectored(bufs);
+        drop(connection);
+        self.throttle(result)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        true
     }
 
     fn flush(&mut self) -> io::Result<()> {
@@ -133,14 +318,36 @@ impl io::Write for WriteHalf {
     }
 }
 
-pub fn split<D1: Into<Vec<u8>>, D2: Into<Vec<u8>>>(
-    stream: TcpStream,
-    connection: Connection,
-    read_buf_cfg: BufCfg<D1>,
-    write_buf_cfg: BufCfg<D2>,
-) -> (ReadHalf, WriteHalf) {
-    assert!(!connection.is_handshaking());
+/// One half's buffer configuration and optional rate limit, bundled so
+/// `split`/`split_early_data` take one argument per half instead of one
+/// argument per knob.
+pub struct HalfCfg<D: Into<Vec<u8>>> {
+    buf: BufCfg<D>,
+    rate_limit: Option<RateLimitCfg>,
+}
+
+impl<D: Into<Vec<u8>>> HalfCfg<D> {
+    /// Configure a half with no rate limit.
+    pub fn new(buf: BufCfg<D>) -> Self {
+        Self {
+            buf,
+            rate_limit: None,
+        }
+    }
+
+    /// Attach a rate limit to this half.
+    pub fn with_rate_limit(mut self, rate_limit: RateLimitCfg) -> Self {
+        self.rate_limit = Some(rate_limit);
+        self
+    }
+}
 
+fn build_halves<S, D1: Into<Vec<u8>>, D2: Into<Vec<u8>>>(
+    stream: S,
+    connection: Connection,
+    read_cfg: HalfCfg<D1>,
+    write_cfg: HalfCfg<D2>,
+) -> (ReadHalf<S>, WriteHalf<S>) {
     let shared = Arc::new(Shared {
         stream,
         connection: Mutex::new(connection),
@@ -148,13 +355,43 @@ pub fn split<D1: Into<Vec<u8>>, D2: Into<Vec<u8>>>(
 
     let read_half = ReadHalf {
         shared: shared.clone(),
-        buf: Buffer::build_from(read_buf_cfg),
+        buf: ReadBuffer::build_from(read_cfg.buf),
+        rate_limiter: read_cfg.rate_limit.map(RateLimiter::build_from),
+        bytes_read: ByteCounter::default(),
     };
 
     let write_half = WriteHalf {
         shared,
-        buf: Buffer::build_from(write_buf_cfg),
+        buf: WriteBuffer::build_from(write_cfg.buf),
+        rate_limiter: write_cfg.rate_limit.map(RateLimiter::build_from),
+        bytes_written: ByteCounter::default(),
     };
 
     (read_half, write_half)
 }
+
+pub fn split<S, D1: Into<Vec<u8>>, D2: Into<Vec<u8>>>(
+    stream: S,
+    connection: Connection,
+    read_cfg: HalfCfg<D1>,
+    write_cfg: HalfCfg<D2>,
+) -> (ReadHalf<S>, WriteHalf<S>) {
+    assert!(!connection.is_handshaking());
+
+    build_halves(stream, connection, read_cfg, write_cfg)
+}
+
+/// Like [`split`], but for a [`Connection`] that may still be mid-handshake.
+/// This lets a `ClientConnection` start writing TLS 1.3 0-RTT early data into
+/// the returned [`WriteHalf`] right away: writes are funneled into
+/// `early_data()` for as long as the handshake allows it, and fall back to
+/// the regular writer once the handshake completes or the server rejects
+/// early data. Reads drive the rest of the handshake to completion as usual.
+pub fn split_early_data<S, D1: Into<Vec<u8>>, D2: Into<Vec<u8>>>(
+    stream: S,
+    connection: Connection,
+    read_cfg: HalfCfg<D1>,
+    write_cfg: HalfCfg<D2>,
+) -> (ReadHalf<S>, WriteHalf<S>) {
+    build_halves(stream, connection, read_cfg, write_cfg)
+}
@@ -0,0 +1,103 @@
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Configures a token-bucket rate limiter for one half of a split connection:
+/// up to `capacity` bytes may move in a single burst, refilling thereafter at
+/// `refill_per_sec` bytes/sec.
+pub struct RateLimitCfg {
+    capacity: u64,
+    refill_per_sec: u64,
+}
+
+impl RateLimitCfg {
+    pub fn new(capacity: u64, refill_per_sec: u64) -> Self {
+        Self {
+            capacity,
+            refill_per_sec,
+        }
+    }
+}
+
+struct State {
+    tokens: u64,
+    last_refill: Instant,
+}
+
+pub(crate) struct RateLimiter {
+    capacity: u64,
+    refill_per_sec: u64,
+    state: Mutex<State>,
+}
+
+impl RateLimiter {
+    pub(crate) fn build_from(cfg: RateLimitCfg) -> Self {
+        Self {
+            capacity: cfg.capacity,
+            refill_per_sec: cfg.refill_per_sec,
+            state: Mutex::new(State {
+                tokens: cfg.capacity,
+                last_refill: Instant::now(),
+            }),
+        }
+    }
+
+    /// Blocks the current thread until `bytes` tokens have refilled, then
+    /// spends them. Call this only after the bytes have actually been moved;
+    /// it never un-does work, it only paces the caller.
+    ///
+    /// `bytes` may exceed the bucket's `capacity` (a single `read`/`write` can
+    /// move more than one burst's worth), so this spends at most `capacity`
+    /// tokens per iteration instead of waiting for a deficit that could never
+    /// be filled in one go.
+    pub(crate) fn throttle(&self, mut bytes: u64) {
+        while bytes > 0 {
+            let spend = bytes.min(self.capacity);
+
+            let wait = {
+                let mut state = self.state.lock().unwrap();
+
+                let now = Instant::now();
+                let elapsed = now.duration_since(state.last_refill).as_secs_f64();
+                state.tokens = ((state.tokens as f64) + elapsed * self.refill_per_sec as f64)
+                    .min(self.capacity as f64) as u64;
+                state.last_refill = now;
+
+                if state.tokens >= spend {
+                    state.tokens -= spend;
+                    None
+                } else {
+                    let deficit = spend - state.tokens;
+                    Some(Duration::from_secs_f64(
+                        deficit as f64 / self.refill_per_sec as f64,
+                    ))
+                }
+            };
+
+            match wait {
+                None => bytes -= spend,
+                Some(duration) => thread::sleep(duration),
+            }
+        }
+    }
+}
+
+/// A cheap byte counter a half updates as it moves plaintext, so callers can
+/// report throughput without wrapping the halves themselves.
+#[derive(Default)]
+pub(crate) struct ByteCounter(AtomicU64);
+
+impl ByteCounter {
+    pub(crate) fn add(&self, bytes: u64) {
+        self.0.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    pub(crate) fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
@@ -1,6 +1,6 @@
 use std::{
     convert::TryInto,
-    io::{BufRead, BufReader, Cursor, Read, Write},
+    io::{self, BufRead, BufReader, Cursor, IoSlice, Read, Write},
     net::{Shutdown, TcpListener, TcpStream},
     sync::Arc,
 };
@@ -71,8 +71,8 @@ fn e2e() {
             let (mut read_half, mut write_half) = rustls_split::split(
                 server_stream,
                 Connection::Server(conn),
-                rustls_split::BufCfg::with_data(buf, BUF_SIZE),
-                rustls_split::BufCfg::with_capacity(BUF_SIZE),
+                rustls_split::HalfCfg::new(rustls_split::BufCfg::with_data(buf, BUF_SIZE)),
+                rustls_split::HalfCfg::new(rustls_split::BufCfg::with_capacity(BUF_SIZE)),
             );
 
             let bytes_copied = std::io::copy(&mut read_half, &mut write_half).unwrap();
@@ -91,8 +91,8 @@ fn e2e() {
     let (mut read_half, mut write_half) = rustls_split::split(
         client_stream,
         Connection::Client(conn),
-        rustls_split::BufCfg::with_capacity(BUF_SIZE),
-        rustls_split::BufCfg::with_capacity(BUF_SIZE),
+        rustls_split::HalfCfg::new(rustls_split::BufCfg::with_capacity(BUF_SIZE)),
+        rustls_split::HalfCfg::new(rustls_split::BufCfg::with_capacity(BUF_SIZE)),
     );
 
     let writer_thread = std::thread::Builder::new()
@@ -124,3 +124,364 @@ fn e2e() {
     reader_thread.join().unwrap();
     writer_thread.join().unwrap();
 }
+
+#[test]
+fn rate_limited_write_does_not_hang_and_counts_bytes() {
+    let (mut server_stream, mut client_stream) = make_tcp_pair();
+
+    const BUF_SIZE: usize = 8192;
+    // Bigger than the rate limiter's burst capacity below, so a single write
+    // has to be spent across more than one token-bucket refill.
+    const DATA_LEN: usize = 32 * 1024;
+
+    let server_thread = std::thread::Builder::new()
+        .name("rate-limit-server".into())
+        .spawn(move || {
+            let server_cfg = make_server_cfg();
+            let mut conn = rustls::ServerConnection::new(server_cfg).unwrap();
+            conn.complete_io(&mut server_stream).unwrap();
+
+            let (mut read_half, mut write_half) = rustls_split::split(
+                server_stream,
+                Connection::Server(conn),
+                rustls_split::HalfCfg::new(rustls_split::BufCfg::with_capacity(BUF_SIZE)),
+                rustls_split::HalfCfg::new(rustls_split::BufCfg::with_capacity(BUF_SIZE)),
+            );
+
+            let bytes_copied = std::io::copy(&mut read_half, &mut write_half).unwrap();
+            assert_eq!(bytes_copied, DATA_LEN as u64);
+            write_half.shutdown(Shutdown::Write).unwrap();
+        })
+        .unwrap();
+
+    let client_cfg = make_client_cfg();
+    let dns = "localhost".try_into().unwrap();
+    let mut conn = rustls::ClientConnection::new(client_cfg, dns).unwrap();
+    conn.complete_io(&mut client_stream).unwrap();
+
+    let (mut read_half, mut write_half) = rustls_split::split(
+        client_stream,
+        Connection::Client(conn),
+        rustls_split::HalfCfg::new(rustls_split::BufCfg::with_capacity(BUF_SIZE))
+            .with_rate_limit(rustls_split::RateLimitCfg::new(1024, 10 * 1024 * 1024)),
+        rustls_split::HalfCfg::new(rustls_split::BufCfg::with_capacity(BUF_SIZE))
+            .with_rate_limit(rustls_split::RateLimitCfg::new(1024, 10 * 1024 * 1024)),
+    );
+
+    let data = vec![0x42u8; DATA_LEN];
+
+    let writer_thread = std::thread::Builder::new()
+        .name("rate-limit-writer".into())
+        .spawn(move || {
+            write_half.write_all(&data).unwrap();
+            assert_eq!(write_half.bytes_written(), DATA_LEN as u64);
+            write_half.shutdown(Shutdown::Write).unwrap();
+        })
+        .unwrap();
+
+    let reader_thread = std::thread::Builder::new()
+        .name("rate-limit-reader".into())
+        .spawn(move || {
+            let mut received = Vec::new();
+            read_half.read_to_end(&mut received).unwrap();
+            assert_eq!(received.len(), DATA_LEN);
+            assert_eq!(read_half.bytes_read(), DATA_LEN as u64);
+        })
+        .unwrap();
+
+    server_thread.join().unwrap();
+    writer_thread.join().unwrap();
+    reader_thread.join().unwrap();
+}
+
+/// Writes `a` and `b` as a single `write_vectored` call, asserting it accepted
+/// both slices in one go (true for these small payloads against an 8KB
+/// ciphertext buffer, so there's no partial write to retry).
+fn write_vectored_all(w: &mut impl Write, a: &[u8], b: &[u8]) -> io::Result<()> {
+    let want = a.len() + b.len();
+    let n = w.write_vectored(&[IoSlice::new(a), IoSlice::new(b)])?;
+    assert_eq!(n, want, "expected a single vectored write to accept both slices");
+    Ok(())
+}
+
+#[test]
+fn would_block_on_a_non_blocking_socket_does_not_lose_a_later_read() {
+    let (mut server_stream, mut client_stream) = make_tcp_pair();
+
+    const BUF_SIZE: usize = 8192;
+    const MSG: &[u8] = b"HELLO";
+
+    // The server's `complete_io` blocks until the client's handshake messages
+    // arrive, so it runs on its own thread while the client drives its side.
+    let server_handshake = std::thread::spawn(move || {
+        let server_cfg = make_server_cfg();
+        let mut server_conn = rustls::ServerConnection::new(server_cfg).unwrap();
+        server_conn.complete_io(&mut server_stream).unwrap();
+        (server_stream, server_conn)
+    });
+
+    let client_cfg = make_client_cfg();
+    let dns = "localhost".try_into().unwrap();
+    let mut client_conn = rustls::ClientConnection::new(client_cfg, dns).unwrap();
+    client_conn.complete_io(&mut client_stream).unwrap();
+    while client_conn.wants_write() {
+        client_conn.write_tls(&mut client_stream).unwrap();
+    }
+
+    let (server_stream, server_conn) = server_handshake.join().unwrap();
+
+    client_stream.set_nonblocking(true).unwrap();
+
+    let (mut client_read, _client_write) = rustls_split::split(
+        client_stream,
+        Connection::Client(client_conn),
+        rustls_split::HalfCfg::new(rustls_split::BufCfg::with_capacity(BUF_SIZE)),
+        rustls_split::HalfCfg::new(rustls_split::BufCfg::with_capacity(BUF_SIZE)),
+    );
+
+    let (_server_read, mut server_write) = rustls_split::split(
+        server_stream,
+        Connection::Server(server_conn),
+        rustls_split::HalfCfg::new(rustls_split::BufCfg::with_capacity(BUF_SIZE)),
+        rustls_split::HalfCfg::new(rustls_split::BufCfg::with_capacity(BUF_SIZE)),
+    );
+
+    // Nothing has been sent yet: the non-blocking socket has no ciphertext
+    // to read, and there's no plaintext already decrypted to hand back
+    // either, so this must surface `WouldBlock` rather than block.
+    let mut buf = [0u8; 16];
+    let err = client_read.read(&mut buf).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::WouldBlock);
+
+    server_write.write_all(MSG).unwrap();
+    server_write.flush().unwrap();
+
+    // Give the loopback pair a moment to deliver the bytes.
+    std::thread::sleep(std::time::Duration::from_millis(50));
+
+    let n = client_read.read(&mut buf).unwrap();
+    assert_eq!(&buf[..n], MSG);
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn async_halves_round_trip() {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let (mut server_stream, mut client_stream) = make_tcp_pair();
+
+    const BUF_SIZE: usize = 8192;
+    const MSG: &[u8] = b"HELLO ASYNC";
+
+    let server_handshake = std::thread::spawn(move || {
+        let server_cfg = make_server_cfg();
+        let mut server_conn = rustls::ServerConnection::new(server_cfg).unwrap();
+        server_conn.complete_io(&mut server_stream).unwrap();
+        (server_stream, server_conn)
+    });
+
+    let client_cfg = make_client_cfg();
+    let dns = "localhost".try_into().unwrap();
+    let mut client_conn = rustls::ClientConnection::new(client_cfg, dns).unwrap();
+    client_conn.complete_io(&mut client_stream).unwrap();
+    while client_conn.wants_write() {
+        client_conn.write_tls(&mut client_stream).unwrap();
+    }
+
+    let (server_stream, server_conn) = server_handshake.join().unwrap();
+
+    server_stream.set_nonblocking(true).unwrap();
+    client_stream.set_nonblocking(true).unwrap();
+
+    let server_stream = tokio::net::TcpStream::from_std(server_stream).unwrap();
+    let client_stream = tokio::net::TcpStream::from_std(client_stream).unwrap();
+
+    let (mut server_read, mut server_write) = rustls_split::split(
+        server_stream,
+        Connection::Server(server_conn),
+        rustls_split::HalfCfg::new(rustls_split::BufCfg::with_capacity(BUF_SIZE)),
+        rustls_split::HalfCfg::new(rustls_split::BufCfg::with_capacity(BUF_SIZE)),
+    );
+
+    let (mut client_read, mut client_write) = rustls_split::split(
+        client_stream,
+        Connection::Client(client_conn),
+        rustls_split::HalfCfg::new(rustls_split::BufCfg::with_capacity(BUF_SIZE)),
+        rustls_split::HalfCfg::new(rustls_split::BufCfg::with_capacity(BUF_SIZE)),
+    );
+
+    let server_task = tokio::spawn(async move {
+        let mut buf = vec![0u8; MSG.len()];
+        server_read.read_exact(&mut buf).await.unwrap();
+        assert_eq!(buf, MSG);
+
+        server_write.write_all(MSG).await.unwrap();
+        server_write.flush().await.unwrap();
+    });
+
+    client_write.write_all(MSG).await.unwrap();
+    client_write.flush().await.unwrap();
+
+    let mut buf = vec![0u8; MSG.len()];
+    client_read.read_exact(&mut buf).await.unwrap();
+    assert_eq!(buf, MSG);
+
+    server_task.await.unwrap();
+}
+
+#[test]
+fn split_early_data_does_not_require_a_completed_handshake() {
+    let (_server_stream, client_stream) = make_tcp_pair();
+
+    const BUF_SIZE: usize = 8192;
+
+    let client_cfg = make_client_cfg();
+    let dns = "localhost".try_into().unwrap();
+    let client_conn = rustls::ClientConnection::new(client_cfg, dns).unwrap();
+    assert!(client_conn.is_handshaking());
+
+    // `split` asserts `!connection.is_handshaking()` and would panic here;
+    // `split_early_data` is specifically meant to allow this so a
+    // `ClientConnection` can start sending TLS 1.3 early data right away.
+    let (_read_half, _write_half) = rustls_split::split_early_data(
+        client_stream,
+        Connection::Client(client_conn),
+        rustls_split::HalfCfg::new(rustls_split::BufCfg::with_capacity(BUF_SIZE)),
+        rustls_split::HalfCfg::new(rustls_split::BufCfg::with_capacity(BUF_SIZE)),
+    );
+}
+
+#[test]
+fn accessors_expose_handshake_details() {
+    let (mut server_stream, mut client_stream) = make_tcp_pair();
+
+    const BUF_SIZE: usize = 8192;
+
+    let server_handshake = std::thread::spawn(move || {
+        let server_cfg = make_server_cfg();
+        let mut server_conn = rustls::ServerConnection::new(server_cfg).unwrap();
+        server_conn.complete_io(&mut server_stream).unwrap();
+        (server_stream, server_conn)
+    });
+
+    let client_cfg = make_client_cfg();
+    let dns = "localhost".try_into().unwrap();
+    let mut client_conn = rustls::ClientConnection::new(client_cfg, dns).unwrap();
+    client_conn.complete_io(&mut client_stream).unwrap();
+    while client_conn.wants_write() {
+        client_conn.write_tls(&mut client_stream).unwrap();
+    }
+
+    let (server_stream, server_conn) = server_handshake.join().unwrap();
+
+    let (server_read, server_write) = rustls_split::split(
+        server_stream,
+        Connection::Server(server_conn),
+        rustls_split::HalfCfg::new(rustls_split::BufCfg::with_capacity(BUF_SIZE)),
+        rustls_split::HalfCfg::new(rustls_split::BufCfg::with_capacity(BUF_SIZE)),
+    );
+
+    let (client_read, client_write) = rustls_split::split(
+        client_stream,
+        Connection::Client(client_conn),
+        rustls_split::HalfCfg::new(rustls_split::BufCfg::with_capacity(BUF_SIZE)),
+        rustls_split::HalfCfg::new(rustls_split::BufCfg::with_capacity(BUF_SIZE)),
+    );
+
+    // Neither side negotiated ALPN.
+    assert_eq!(client_read.alpn_protocol(), None);
+    assert_eq!(client_write.alpn_protocol(), None);
+    assert_eq!(server_read.alpn_protocol(), None);
+    assert_eq!(server_write.alpn_protocol(), None);
+
+    // The TLS version is visible from both halves and agrees with the peer.
+    let client_version = client_read.protocol_version();
+    assert!(client_version.is_some());
+    assert_eq!(client_version, client_write.protocol_version());
+    assert_eq!(client_version, server_read.protocol_version());
+    assert_eq!(client_version, server_write.protocol_version());
+
+    // The client received and validated the server's certificate chain; the
+    // server, configured with `with_no_client_auth`, never asked for one.
+    assert_eq!(client_read.peer_certificates(), Some(vec![read_cert()]));
+    assert_eq!(client_write.peer_certificates(), Some(vec![read_cert()]));
+    assert_eq!(server_read.peer_certificates(), None);
+    assert_eq!(server_write.peer_certificates(), None);
+}
+
+#[test]
+fn write_vectored_round_trips_and_wraps_the_ciphertext_buffer() {
+    let (mut server_stream, mut client_stream) = make_tcp_pair();
+
+    // Enough iterations to cycle the 8KB ciphertext write buffer many times
+    // over, exercising `WriteBuffer`'s wraparound rather than just its
+    // initial fill.
+    const ITERS: u64 = 20_000;
+    const PART_A: &[u8] = b"HELLO ";
+    const PART_B: &[u8] = b"WORLD";
+    const MSG_LEN: usize = PART_A.len() + PART_B.len();
+
+    const BUF_SIZE: usize = 8192;
+
+    let server_thread = std::thread::Builder::new()
+        .name("vectored-server".into())
+        .spawn(move || {
+            let server_cfg = make_server_cfg();
+            let mut conn = rustls::ServerConnection::new(server_cfg).unwrap();
+            conn.complete_io(&mut server_stream).unwrap();
+
+            let (mut read_half, mut write_half) = rustls_split::split(
+                server_stream,
+                Connection::Server(conn),
+                rustls_split::HalfCfg::new(rustls_split::BufCfg::with_capacity(BUF_SIZE)),
+                rustls_split::HalfCfg::new(rustls_split::BufCfg::with_capacity(BUF_SIZE)),
+            );
+
+            let bytes_copied = std::io::copy(&mut read_half, &mut write_half).unwrap();
+            assert_eq!(bytes_copied, ITERS * MSG_LEN as u64);
+            write_half.shutdown(Shutdown::Write).unwrap();
+        })
+        .unwrap();
+
+    let client_cfg = make_client_cfg();
+    let dns = "localhost".try_into().unwrap();
+    let mut conn = rustls::ClientConnection::new(client_cfg, dns).unwrap();
+    conn.complete_io(&mut client_stream).unwrap();
+
+    let (mut read_half, mut write_half) = rustls_split::split(
+        client_stream,
+        Connection::Client(conn),
+        rustls_split::HalfCfg::new(rustls_split::BufCfg::with_capacity(BUF_SIZE)),
+        rustls_split::HalfCfg::new(rustls_split::BufCfg::with_capacity(BUF_SIZE)),
+    );
+
+    let writer_thread = std::thread::Builder::new()
+        .name("vectored-writer".into())
+        .spawn(move || {
+            for _ in 0..ITERS {
+                write_vectored_all(&mut write_half, PART_A, PART_B).unwrap();
+            }
+
+            write_half.shutdown(Shutdown::Write).unwrap();
+        })
+        .unwrap();
+
+    let reader_thread = std::thread::Builder::new()
+        .name("vectored-reader".into())
+        .spawn(move || {
+            let mut buf = vec![0u8; MSG_LEN];
+
+            for _ in 0..ITERS {
+                read_half.read_exact(&mut buf).unwrap();
+                assert_eq!(&buf[..PART_A.len()], PART_A);
+                assert_eq!(&buf[PART_A.len()..], PART_B);
+            }
+
+            assert_eq!(0, read_half.read(&mut buf).unwrap());
+        })
+        .unwrap();
+
+    server_thread.join().unwrap();
+    reader_thread.join().unwrap();
+    writer_thread.join().unwrap();
+}